@@ -0,0 +1,58 @@
+//! Zips the vendored typeshed stubs into `$OUT_DIR/vendored_typeshed.zip` so that
+//! `crate::vfs::VENDORED_TYPESHED_ZIP` can embed them in the compiled binary via `include_bytes!`.
+//!
+//! Requires the `zip` crate as a build-dependency in `Cargo.toml`, in addition to the runtime
+//! dependency used by `src/vfs.rs` to read the embedded archive back out.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+fn main() {
+    let vendor_dir = Path::new("vendor/typeshed");
+    println!("cargo:rerun-if-changed={}", vendor_dir.display());
+
+    let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR to be set by Cargo");
+    let zip_path = Path::new(&out_dir).join("vendored_typeshed.zip");
+
+    let file = File::create(&zip_path).expect("failed to create vendored typeshed archive");
+    let mut writer = ZipWriter::new(file);
+
+    zip_directory(&mut writer, vendor_dir, vendor_dir)
+        .expect("failed to zip vendored typeshed stubs");
+    writer
+        .finish()
+        .expect("failed to finalize vendored typeshed archive");
+}
+
+/// Recursively adds every file under `dir` to `writer`, using its path relative to `root`
+/// (normalized to `/` separators) as the zip entry name.
+fn zip_directory(writer: &mut ZipWriter<File>, root: &Path, dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.is_dir() {
+            zip_directory(writer, root, &path)?;
+            continue;
+        }
+
+        let entry_name = path
+            .strip_prefix(root)
+            .expect("entry to be inside root")
+            .to_str()
+            .expect("vendored typeshed path to be valid UTF-8")
+            .replace('\\', "/");
+
+        writer
+            .start_file(entry_name, FileOptions::default())
+            .map_err(io::Error::other)?;
+        writer.write_all(&fs::read(&path)?)?;
+    }
+
+    Ok(())
+}
@@ -1,13 +1,21 @@
-use std::sync::Arc;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 use countme::Count;
 use dashmap::mapref::entry::Entry;
+use rustc_hash::FxHasher;
+use zip::ZipArchive;
 
+pub use loader::{Change, ChangeKind};
 pub use path::{VendoredPath, VendoredPathBuf, VfsPath};
 
-use crate::file_system::{FileRevision, FileSystemPath};
+use crate::file_system::{FileRevision, FileSystemPath, FileSystemPathBuf};
 use crate::{Db, FxDashMap};
 
+pub mod loader;
 mod path;
 
 /// Virtual file system that supports files from different sources.
@@ -45,6 +53,175 @@ struct VfsInner {
     ///
     files_by_path: FxDashMap<VfsPath, VfsFile>,
     vendored: VendoredVfs,
+
+    /// In-memory contents of files that an editor has open but that haven't been saved to disk.
+    ///
+    /// An overlay always takes precedence over the file's on-disk content. Removing an overlay
+    /// falls back to whatever is on disk (or to [`FileStatus::Deleted`] if the file doesn't exist).
+    overlays: FxDashMap<FileSystemPathBuf, String>,
+
+    /// Monotonically increasing counter used to mint a fresh [`FileRevision`] every time an
+    /// overlay is set or removed, so that salsa always sees the file as having changed.
+    overlay_revision: AtomicU64,
+
+    /// How [`VfsFile::revision`] is computed for files backed by the file system.
+    revision_mode: RevisionMode,
+
+    /// Cache for [`RevisionMode::ContentHash`], keyed by path: the metadata-derived revision that
+    /// was in effect the last time the file's content was hashed, together with the hash that
+    /// produced. Lets a lookup skip re-reading and re-hashing a file whose mtime/size haven't
+    /// changed since then, by returning the cached hash directly.
+    content_hash_cache: FxDashMap<FileSystemPathBuf, (FileRevision, FileRevision)>,
+
+    /// The changes accumulated since the last call to [`Vfs::take_changes`].
+    changes: Mutex<Vec<VfsChange>>,
+}
+
+impl VfsInner {
+    /// Mints a [`FileRevision`] guaranteed to be different from the previous one returned by
+    /// this method, for use whenever an overlay is set or removed.
+    fn next_overlay_revision(&self) -> FileRevision {
+        FileRevision::new(self.overlay_revision.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    /// Computes the [`FileRevision`] for `path` given its metadata-derived `metadata_revision`,
+    /// honoring [`VfsInner::revision_mode`].
+    ///
+    /// In [`RevisionMode::ContentHash`] mode, `metadata_revision` is used as a cheap pre-filter:
+    /// if it matches what was used to compute the cached hash last time, the file's content is
+    /// assumed unchanged and the cached hash is returned without touching disk.
+    fn content_revision(
+        &self,
+        db: &dyn Db,
+        path: &FileSystemPath,
+        metadata_revision: FileRevision,
+    ) -> FileRevision {
+        match self.revision_mode {
+            RevisionMode::Metadata => metadata_revision,
+            RevisionMode::ContentHash => self
+                .cached_content_hash(path, metadata_revision)
+                .unwrap_or_else(|| {
+                    let hash = hash_revision(&db.file_system().read(path).unwrap_or_default());
+                    self.content_hash_cache
+                        .insert(path.to_path_buf(), (metadata_revision, hash));
+                    hash
+                }),
+        }
+    }
+
+    /// Returns the cached content hash for `path` if it was computed under the same
+    /// `metadata_revision`, i.e. nothing has changed since that hash was cached.
+    fn cached_content_hash(
+        &self,
+        path: &FileSystemPath,
+        metadata_revision: FileRevision,
+    ) -> Option<FileRevision> {
+        let (cached_metadata_revision, hash) = *self.content_hash_cache.get(&path.to_path_buf())?;
+
+        (cached_metadata_revision == metadata_revision).then_some(hash)
+    }
+
+    /// Appends an entry to the change log consumed by [`Vfs::take_changes`].
+    fn record_change(&self, path: VfsPath, kind: VfsChangeKind) {
+        self.changes.lock().unwrap().push(VfsChange { path, kind });
+    }
+}
+
+/// A single change recorded by [`Vfs`] as files are looked up, edited, or invalidated.
+///
+/// Collected via [`Vfs::take_changes`] so that a caller (e.g. an LSP server's main loop) can
+/// gather every change observed during one tick and apply it to salsa as a single, coherent
+/// revision bump instead of interleaving reads and writes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VfsChange {
+    pub path: VfsPath,
+    pub kind: VfsChangeKind,
+}
+
+/// What kind of change [`VfsChange`] describes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VfsChangeKind {
+    Created,
+    Changed,
+    Deleted,
+}
+
+/// Controls how [`Vfs`] computes the [`FileRevision`] of a file backed by the file system.
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RevisionMode {
+    /// Derive the revision from the file's metadata (mtime and size).
+    ///
+    /// This is the cheapest option because it never requires reading a file's content, but it
+    /// invalidates every query depending on the file whenever its mtime changes, even if the
+    /// file's bytes didn't. This is the right trade-off for one-shot CLI runs that read every
+    /// file's content anyway and never need to distinguish a touch from a real edit.
+    #[default]
+    Metadata,
+
+    /// Derive the revision from a hash of the file's content.
+    ///
+    /// Requires reading the file's content, so a cheap metadata comparison is used as a
+    /// pre-filter: the content is only re-read and re-hashed when the metadata-derived revision
+    /// has changed since the last time it was computed. This avoids invalidating dependent salsa
+    /// queries when a file is touched but not otherwise modified, which matters for long-running
+    /// servers where spurious invalidation means redundant re-analysis.
+    ContentHash,
+}
+
+/// Computes a fast, non-cryptographic hash of `content` for use as a [`FileRevision`].
+fn hash_revision(content: &str) -> FileRevision {
+    let mut hasher = FxHasher::default();
+    content.hash(&mut hasher);
+    FileRevision::new(hasher.finish())
+}
+
+/// A path expressed relative to the containing directory of another [`VfsFile`].
+///
+/// Python import resolution (plain imports as well as `from . import x`) needs to locate files
+/// relative to the importing module rather than by absolute path. `AnchoredPath` pairs the
+/// relative string with the file it's anchored to, so [`Vfs::resolve_anchored`] can do the
+/// directory-joining and normalization once, uniformly, for every caller.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AnchoredPath<'a> {
+    pub anchor: VfsFile,
+    pub path: &'a str,
+}
+
+impl<'a> AnchoredPath<'a> {
+    pub fn new(anchor: VfsFile, path: &'a str) -> Self {
+        Self { anchor, path }
+    }
+}
+
+/// Joins `relative` onto the directory `base`, collapsing `.` and `..` components.
+///
+/// Returns `None` if the `..` components climb above the first component of `base` (or above
+/// `/` for an absolute `base`); a `..` that only leaves `base` for one of its ancestors is fine.
+fn join_normalized(base: &str, relative: &str) -> Option<String> {
+    let is_absolute = base.starts_with('/');
+
+    let mut segments: Vec<&str> = base
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    for component in relative.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    let joined = segments.join("/");
+
+    Some(if is_absolute {
+        format!("/{joined}")
+    } else {
+        joined
+    })
 }
 
 impl Vfs {
@@ -58,6 +235,15 @@ impl Vfs {
         }
     }
 
+    /// Changes how this [`Vfs`] computes the [`FileRevision`] of file-system-backed files.
+    ///
+    /// ## Panics
+    /// If there are pending snapshots referencing this `Vfs` instance.
+    pub fn set_revision_mode(&mut self, mode: RevisionMode) {
+        let inner = Arc::get_mut(&mut self.inner).unwrap();
+        inner.revision_mode = mode;
+    }
+
     /// Looks up a file by its path.
     ///
     /// For a non-existing file, creates a new salsa [`VfsFile`] ingredient and stores it for future lookups.
@@ -65,34 +251,186 @@ impl Vfs {
     /// The operation always succeeds even if the path doesn't exist on disk, isn't accessible or if the path points to a directory.
     /// In these cases, a file with status [`FileStatus::Deleted`] is returned.
     pub fn file(&self, db: &dyn Db, path: &FileSystemPath) -> VfsFile {
+        self.file_impl(db, path, true)
+    }
+
+    /// Implements [`Vfs::file`], with `record_deleted` controlling whether a file seen for the
+    /// first time and found not to exist is recorded as a [`VfsChangeKind::Deleted`] change.
+    ///
+    /// [`Vfs::resolve_anchored`] passes `false` here: it probes candidate neighbor paths that
+    /// usually don't exist (e.g. trying `__init__.py` next to a module), and a negative probe
+    /// isn't an observed change worth surfacing through [`Vfs::take_changes`]. A file that *does*
+    /// exist is always recorded, since that's a real, newly-observed file regardless of how it
+    /// was looked up.
+    fn file_impl(&self, db: &dyn Db, path: &FileSystemPath, record_deleted: bool) -> VfsFile {
         *self
             .inner
             .files_by_path
             .entry(VfsPath::FileSystem(path.to_path_buf()))
             .or_insert_with(|| {
-                let metadata = db.file_system().metadata(path);
+                let vfs_path = VfsPath::FileSystem(path.to_path_buf());
 
-                match metadata {
-                    Ok(metadata) if metadata.file_type().is_file() => VfsFile::new(
+                if self.inner.overlays.contains_key(&path.to_path_buf()) {
+                    self.inner
+                        .record_change(vfs_path.clone(), VfsChangeKind::Created);
+                    return VfsFile::new(
                         db,
-                        VfsPath::FileSystem(path.to_path_buf()),
-                        metadata.permissions(),
-                        metadata.revision(),
-                        FileStatus::Exists,
-                        Count::default(),
-                    ),
-                    _ => VfsFile::new(
-                        db,
-                        VfsPath::FileSystem(path.to_path_buf()),
+                        vfs_path,
                         None,
-                        FileRevision::zero(),
-                        FileStatus::Deleted,
+                        self.inner.next_overlay_revision(),
+                        FileStatus::Exists,
                         Count::default(),
-                    ),
+                    );
+                }
+
+                let metadata = db.file_system().metadata(path);
+
+                match metadata {
+                    Ok(metadata) if metadata.file_type().is_file() => {
+                        self.inner
+                            .record_change(vfs_path.clone(), VfsChangeKind::Created);
+                        VfsFile::new(
+                            db,
+                            vfs_path,
+                            metadata.permissions(),
+                            self.inner.content_revision(db, path, metadata.revision()),
+                            FileStatus::Exists,
+                            Count::default(),
+                        )
+                    }
+                    _ => {
+                        if record_deleted {
+                            self.inner
+                                .record_change(vfs_path.clone(), VfsChangeKind::Deleted);
+                        }
+                        VfsFile::new(
+                            db,
+                            vfs_path,
+                            None,
+                            FileRevision::zero(),
+                            FileStatus::Deleted,
+                            Count::default(),
+                        )
+                    }
                 }
             })
     }
 
+    /// Drains and returns every [`VfsChange`] recorded since the last call to this method (or
+    /// since the [`Vfs`] was created, for the first call).
+    pub fn take_changes(&self) -> Vec<VfsChange> {
+        std::mem::take(&mut *self.inner.changes.lock().unwrap())
+    }
+
+    /// Sets the in-memory content of `path` to `contents`, shadowing whatever is on disk.
+    ///
+    /// This is how an editor tells the [`Vfs`] about a buffer that has unsaved changes. If a
+    /// [`VfsFile`] for `path` already exists, its status is flipped to [`FileStatus::Exists`] and
+    /// its revision is bumped so that dependent salsa queries re-run and observe the overlay.
+    pub fn set_overlay(&self, db: &mut dyn Db, path: FileSystemPathBuf, contents: String) {
+        self.inner.overlays.insert(path.clone(), contents);
+        // `touch_overlay` calls `Vfs::file`, which records `Created` the first time `path` is
+        // seen; record `Changed` after it so `take_changes` sees changes in chronological order.
+        self.touch_overlay(db, &path, FileStatus::Exists);
+        self.inner
+            .record_change(VfsPath::FileSystem(path), VfsChangeKind::Changed);
+    }
+
+    /// Removes the in-memory overlay for `path`, falling back to the file's on-disk content (or
+    /// [`FileStatus::Deleted`] if the file no longer exists on disk either).
+    pub fn remove_overlay(&self, db: &mut dyn Db, path: &FileSystemPath) {
+        if self.inner.overlays.remove(&path.to_path_buf()).is_some() {
+            let status = match db.file_system().metadata(path) {
+                Ok(metadata) if metadata.file_type().is_file() => FileStatus::Exists,
+                _ => FileStatus::Deleted,
+            };
+
+            // See `set_overlay`: record after `touch_overlay` so `take_changes` sees changes in
+            // chronological order relative to the `Created`/`Deleted` change `Vfs::file` records
+            // the first time `path` is seen.
+            self.touch_overlay(db, path, status);
+            self.inner.record_change(
+                VfsPath::FileSystem(path.to_path_buf()),
+                VfsChangeKind::Changed,
+            );
+        }
+    }
+
+    /// Updates the status and revision of the [`VfsFile`] for `path`, creating it first if it
+    /// doesn't exist yet.
+    fn touch_overlay(&self, db: &mut dyn Db, path: &FileSystemPath, status: FileStatus) {
+        let file = self.file(db, path);
+        let revision = self.inner.next_overlay_revision();
+
+        file.set_status(db).to(status);
+        file.set_revision(db).to(revision);
+    }
+
+    /// Applies a batch of [`Change`]s reported by a [`loader::Handle`], updating the status,
+    /// permissions and revision of every affected [`VfsFile`] so that dependent salsa queries
+    /// re-run.
+    ///
+    /// A change for a path that has an active overlay is ignored: an open, unsaved buffer always
+    /// takes precedence over what's on disk.
+    pub fn apply_changes(&self, db: &mut dyn Db, changes: impl IntoIterator<Item = Change>) {
+        for change in changes {
+            if self.inner.overlays.contains_key(&change.path) {
+                continue;
+            }
+
+            let vfs_path = VfsPath::FileSystem(change.path.clone());
+            // `file()` already records a `Created`/`Deleted` change the first time a path is
+            // seen; only record here for paths it already knew about, to avoid a duplicate entry.
+            let already_tracked = self.inner.files_by_path.contains_key(&vfs_path);
+            let file = self.file(db, &change.path);
+
+            match change.kind {
+                ChangeKind::Deleted => {
+                    if already_tracked {
+                        self.inner.record_change(vfs_path, VfsChangeKind::Deleted);
+                    }
+                    file.set_status(db).to(FileStatus::Deleted);
+                    file.set_permissions(db).to(None);
+                    file.set_revision(db).to(FileRevision::zero());
+                }
+                ChangeKind::Created | ChangeKind::Modified => {
+                    if already_tracked {
+                        let change_kind = match change.kind {
+                            ChangeKind::Created => VfsChangeKind::Created,
+                            ChangeKind::Modified => VfsChangeKind::Changed,
+                            ChangeKind::Deleted => unreachable!(),
+                        };
+                        self.inner.record_change(vfs_path, change_kind);
+                    }
+
+                    let metadata = db.file_system().metadata(&change.path);
+
+                    let (permissions, metadata_revision) = match &metadata {
+                        Ok(metadata) => (metadata.permissions(), metadata.revision()),
+                        Err(_) => (None, FileRevision::zero()),
+                    };
+
+                    let revision = match (&change.content, self.inner.revision_mode) {
+                        (Some(content), RevisionMode::ContentHash) => {
+                            let hash = hash_revision(content);
+                            self.inner
+                                .content_hash_cache
+                                .insert(change.path.clone(), (metadata_revision, hash));
+                            hash
+                        }
+                        _ => self
+                            .inner
+                            .content_revision(db, &change.path, metadata_revision),
+                    };
+
+                    file.set_status(db).to(FileStatus::Exists);
+                    file.set_permissions(db).to(permissions);
+                    file.set_revision(db).to(revision);
+                }
+            }
+        }
+    }
+
     /// Lookups a vendored file by its path. Returns `Some` if a vendored file for the given path
     /// exists and `None` otherwise.
     pub fn vendored(&self, db: &dyn Db, path: &VendoredPath) -> Option<VfsFile> {
@@ -123,6 +461,33 @@ impl Vfs {
         Some(file)
     }
 
+    /// Resolves `anchored.path` relative to `anchored.anchor`'s containing directory, normalizing
+    /// `.` and `..` components, and returns the interned [`VfsFile`] for the result.
+    ///
+    /// This is the single, cache-friendly primitive the rest of the crate uses for neighbor-file
+    /// traversal (e.g. resolving `from . import x`), and it works uniformly whether the anchor is
+    /// a [`VfsPath::FileSystem`] or a [`VfsPath::Vendored`] path. Returns `None` if the `..`
+    /// components climb above the first component of the anchor's directory, or if the resulting
+    /// file doesn't exist. The [`Vfs`] keeps no set of roots, so no other check is made on where
+    /// the resolved path lies.
+    pub fn resolve_anchored(&self, db: &dyn Db, anchored: AnchoredPath) -> Option<VfsFile> {
+        match anchored.anchor.path(db) {
+            VfsPath::FileSystem(anchor_path) => {
+                let directory = anchor_path.parent()?;
+                let resolved = join_normalized(directory.as_str(), anchored.path)?;
+                let file = self.file_impl(db, FileSystemPath::new(&resolved), false);
+
+                (file.status(db) == FileStatus::Exists).then_some(file)
+            }
+            VfsPath::Vendored(anchor_path) => {
+                let directory = anchor_path.parent()?;
+                let resolved = join_normalized(directory.as_str(), anchored.path)?;
+
+                self.vendored(db, VendoredPath::new(&resolved))
+            }
+        }
+    }
+
     /// Stubs out the vendored files with the given content.
     ///
     /// ## Panics
@@ -137,7 +502,9 @@ impl Vfs {
         let stubbed = FxDashMap::default();
 
         for (path, content) in vendored {
-            stubbed.insert(path.as_ref().to_path_buf(), content.to_string());
+            let path = path.as_ref().to_path_buf();
+            inner.record_change(VfsPath::Vendored(path.clone()), VfsChangeKind::Changed);
+            stubbed.insert(path, content.to_string());
         }
 
         inner.vendored = VendoredVfs::Stubbed(stubbed);
@@ -153,7 +520,12 @@ impl Vfs {
 
     fn read(&self, db: &dyn Db, path: &VfsPath) -> String {
         match path {
-            VfsPath::FileSystem(path) => db.file_system().read(path).unwrap_or_default(),
+            VfsPath::FileSystem(path) => self
+                .inner
+                .overlays
+                .get(path)
+                .map(|contents| contents.clone())
+                .unwrap_or_else(|| db.file_system().read(path).unwrap_or_default()),
 
             VfsPath::Vendored(vendored) => db
                 .vfs()
@@ -233,17 +605,53 @@ pub enum FileStatus {
     Deleted,
 }
 
-#[derive(Default, Debug)]
+/// The vendored typeshed stubs distributed with the Ruff binary, zipped up at build time and
+/// embedded directly in the executable so that it works without access to the file system.
+static VENDORED_TYPESHED_ZIP: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/vendored_typeshed.zip"));
+
 enum VendoredVfs {
-    #[default]
-    Real,
+    /// The raw bytes of the [`VENDORED_TYPESHED_ZIP`] archive embedded in the binary.
+    ///
+    /// A [`ZipArchive`] is opened from these bytes fresh for every lookup (see
+    /// [`VendoredVfs::open_real_archive`]) rather than parsed once and shared behind a lock:
+    /// parsing a zip's central directory from an in-memory byte slice is cheap, and this way
+    /// concurrent vendored-file lookups (e.g. from parallel salsa queries) never serialize on a
+    /// single mutex, and the bytes are never touched at all unless a `Real` lookup actually
+    /// happens - `Default` just copies this slice reference, so constructing a [`Vfs`] can't fail
+    /// even if the embedded archive is somehow invalid.
+    ///
+    /// The archive is immutable for the lifetime of the process, so a file's [`FileRevision`]
+    /// never needs to change; it's derived once from the entry's CRC-32, which is part of the
+    /// zip format and therefore free to read.
+    Real(&'static [u8]),
     Stubbed(FxDashMap<VendoredPathBuf, String>),
 }
 
+impl Default for VendoredVfs {
+    fn default() -> Self {
+        VendoredVfs::Real(VENDORED_TYPESHED_ZIP)
+    }
+}
+
+impl fmt::Debug for VendoredVfs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VendoredVfs::Real(_) => f.debug_tuple("Real").finish(),
+            VendoredVfs::Stubbed(stubbed) => f.debug_tuple("Stubbed").field(stubbed).finish(),
+        }
+    }
+}
+
 impl VendoredVfs {
     fn revision(&self, path: &VendoredPath) -> Option<FileRevision> {
         match self {
-            VendoredVfs::Real => todo!(),
+            VendoredVfs::Real(bytes) => {
+                let mut archive = Self::open_real_archive(bytes);
+                let entry = archive.by_name(&Self::archive_path(path)).ok()?;
+
+                Some(FileRevision::new(u64::from(entry.crc32())))
+            }
             VendoredVfs::Stubbed(stubbed) => stubbed
                 .contains_key(&path.to_path_buf())
                 .then_some(FileRevision::new(1)),
@@ -252,19 +660,45 @@ impl VendoredVfs {
 
     fn read(&self, path: &VendoredPath) -> Option<String> {
         match self {
-            VendoredVfs::Real => todo!(),
+            VendoredVfs::Real(bytes) => {
+                let mut archive = Self::open_real_archive(bytes);
+                let mut entry = archive.by_name(&Self::archive_path(path)).ok()?;
+
+                let mut contents = String::new();
+                entry.read_to_string(&mut contents).ok()?;
+
+                Some(contents)
+            }
             VendoredVfs::Stubbed(stubbed) => stubbed.get(&path.to_path_buf()).as_deref().cloned(),
         }
     }
+
+    /// Parses `bytes` as a zip archive.
+    ///
+    /// ## Panics
+    /// If `bytes` isn't a valid zip archive.
+    fn open_real_archive(bytes: &'static [u8]) -> ZipArchive<Cursor<&'static [u8]>> {
+        ZipArchive::new(Cursor::new(bytes))
+            .expect("vendored typeshed archive to be a valid zip file")
+    }
+
+    /// Converts `path` to the `/`-separated form used for entry names inside the zip archive,
+    /// regardless of the platform's own path separator.
+    fn archive_path(path: &VendoredPath) -> String {
+        path.as_str().replace('\\', "/")
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::file_system::{FileRevision, FileSystemPath};
+    use crate::file_system::{FileRevision, FileSystemPath, FileSystemPathBuf};
     use crate::tests::TestDb;
-    use crate::vfs::{FileStatus, VendoredPath};
+    use crate::vfs::loader::{Change, ChangeKind};
+    use crate::vfs::{AnchoredPath, FileStatus, RevisionMode, VendoredPath, VfsChangeKind};
     use crate::Db;
 
+    use super::VfsInner;
+
     #[test]
     fn file_system_existing_file() {
         let mut db = TestDb::new();
@@ -315,4 +749,398 @@ mod tests {
 
         assert_eq!(db.vendored_file(VendoredPath::new("test.py")), None);
     }
+
+    #[test]
+    fn overlay_shadows_file_system_content() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('on disk')")]);
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let test = db.file(FileSystemPath::new("test.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_eq!(&test.read(&db), "print('unsaved')");
+    }
+
+    #[test]
+    fn overlay_on_non_existing_file_reports_exists() {
+        let mut db = TestDb::new();
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let test = db.file(FileSystemPath::new("test.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_eq!(&test.read(&db), "print('unsaved')");
+    }
+
+    #[test]
+    fn removing_overlay_falls_back_to_file_system() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('on disk')")]);
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let revision_with_overlay = db.file(FileSystemPath::new("test.py")).revision(&db);
+
+        let vfs = db.vfs();
+        vfs.remove_overlay(&mut db, FileSystemPath::new("test.py"));
+
+        let test = db.file(FileSystemPath::new("test.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_ne!(test.revision(&db), revision_with_overlay);
+        assert_eq!(&test.read(&db), "print('on disk')");
+    }
+
+    #[test]
+    fn removing_overlay_with_no_file_on_disk_reports_deleted() {
+        let mut db = TestDb::new();
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let vfs = db.vfs();
+        vfs.remove_overlay(&mut db, FileSystemPath::new("test.py"));
+
+        let test = db.file(FileSystemPath::new("test.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Deleted);
+    }
+
+    #[test]
+    fn content_hash_revision_depends_on_content() {
+        let mut db = TestDb::new();
+        db.vfs_mut().set_revision_mode(RevisionMode::ContentHash);
+
+        db.file_system_mut()
+            .write_files([("a.py", "print('a')"), ("b.py", "print('b')")]);
+
+        let a = db.file(FileSystemPath::new("a.py"));
+        let b = db.file(FileSystemPath::new("b.py"));
+
+        assert_ne!(a.revision(&db), b.revision(&db));
+    }
+
+    #[test]
+    fn content_hash_revision_is_stable_for_identical_content() {
+        let mut db = TestDb::new();
+        db.vfs_mut().set_revision_mode(RevisionMode::ContentHash);
+
+        db.file_system_mut()
+            .write_files([("a.py", "print('same')"), ("b.py", "print('same')")]);
+
+        let a = db.file(FileSystemPath::new("a.py"));
+        let b = db.file(FileSystemPath::new("b.py"));
+
+        assert_eq!(a.revision(&db), b.revision(&db));
+    }
+
+    #[test]
+    fn content_hash_cache_skips_rehash_when_metadata_unchanged() {
+        let mut db = TestDb::new();
+        let inner = VfsInner {
+            revision_mode: RevisionMode::ContentHash,
+            ..VfsInner::default()
+        };
+        let metadata_revision = FileRevision::new(1);
+        let path = FileSystemPath::new("test.py");
+
+        let first = inner.content_revision(&db, path, metadata_revision);
+
+        // The content changes on disk, but `metadata_revision` is unchanged: the pre-filter
+        // should return the cached hash without re-reading, so the revision doesn't move.
+        db.file_system_mut()
+            .write_files([("test.py", "print('changed')")]);
+        let second = inner.content_revision(&db, path, metadata_revision);
+
+        assert_eq!(first, second);
+
+        // Once `metadata_revision` itself changes, the cache is invalidated and the (now
+        // different) content is re-read and re-hashed.
+        let third = inner.content_revision(&db, path, FileRevision::new(2));
+
+        assert_ne!(second, third);
+    }
+
+    #[test]
+    fn apply_changes_updates_existing_file() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('before')")]);
+
+        let test = db.file(FileSystemPath::new("test.py"));
+        let revision_before = test.revision(&db);
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('after')")]);
+
+        let vfs = db.vfs();
+        vfs.apply_changes(
+            &mut db,
+            [Change::new(
+                FileSystemPathBuf::from("test.py"),
+                ChangeKind::Modified,
+            )],
+        );
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_ne!(test.revision(&db), revision_before);
+        assert_eq!(&test.read(&db), "print('after')");
+    }
+
+    #[test]
+    fn apply_changes_marks_deleted_file() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('before')")]);
+
+        let test = db.file(FileSystemPath::new("test.py"));
+        assert_eq!(test.status(&db), FileStatus::Exists);
+
+        let vfs = db.vfs();
+        vfs.apply_changes(
+            &mut db,
+            [Change::new(
+                FileSystemPathBuf::from("test.py"),
+                ChangeKind::Deleted,
+            )],
+        );
+
+        assert_eq!(test.status(&db), FileStatus::Deleted);
+    }
+
+    #[test]
+    fn apply_changes_ignores_overlaid_file() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('on disk')")]);
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let vfs = db.vfs();
+        vfs.apply_changes(
+            &mut db,
+            [Change::new(
+                FileSystemPathBuf::from("test.py"),
+                ChangeKind::Deleted,
+            )],
+        );
+
+        let test = db.file(FileSystemPath::new("test.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_eq!(&test.read(&db), "print('unsaved')");
+    }
+
+    #[test]
+    fn take_changes_drains_accumulated_changes() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('before')")]);
+
+        // Observing the file for the first time records a `Created` change.
+        db.file(FileSystemPath::new("test.py"));
+
+        db.file_system_mut()
+            .write_files([("test.py", "print('after')")]);
+
+        let vfs = db.vfs();
+        vfs.apply_changes(
+            &mut db,
+            [Change::new(
+                FileSystemPathBuf::from("test.py"),
+                ChangeKind::Modified,
+            )],
+        );
+
+        let changes = db.vfs().take_changes();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, VfsChangeKind::Created);
+        assert_eq!(changes[1].kind, VfsChangeKind::Changed);
+
+        // A second call without anything happening in between returns nothing.
+        assert_eq!(db.vfs().take_changes(), Vec::new());
+    }
+
+    #[test]
+    fn take_changes_orders_overlay_changes_after_creation() {
+        let mut db = TestDb::new();
+
+        let vfs = db.vfs();
+        vfs.set_overlay(
+            &mut db,
+            FileSystemPathBuf::from("test.py"),
+            "print('unsaved')".to_string(),
+        );
+
+        let changes = db.vfs().take_changes();
+
+        // `test.py` is seen for the first time by `set_overlay`, so it's recorded as `Created`;
+        // the overlay's own `Changed` entry must come after, not before.
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, VfsChangeKind::Created);
+        assert_eq!(changes[1].kind, VfsChangeKind::Changed);
+    }
+
+    #[test]
+    fn resolve_anchored_sibling_file() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("src/a.py", "import b"), ("src/b.py", "x = 1")]);
+
+        let a = db.file(FileSystemPath::new("src/a.py"));
+        let resolved = db.vfs().resolve_anchored(&db, AnchoredPath::new(a, "b.py"));
+
+        assert_eq!(resolved, Some(db.file(FileSystemPath::new("src/b.py"))));
+    }
+
+    #[test]
+    fn resolve_anchored_parent_directory() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("src/pkg/a.py", ""), ("src/b.py", "")]);
+
+        let a = db.file(FileSystemPath::new("src/pkg/a.py"));
+        let resolved = db
+            .vfs()
+            .resolve_anchored(&db, AnchoredPath::new(a, "../b.py"));
+
+        assert_eq!(resolved, Some(db.file(FileSystemPath::new("src/b.py"))));
+    }
+
+    #[test]
+    fn resolve_anchored_parent_directory_absolute_path() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut()
+            .write_files([("/src/pkg/a.py", ""), ("/src/b.py", "")]);
+
+        let a = db.file(FileSystemPath::new("/src/pkg/a.py"));
+        let resolved = db
+            .vfs()
+            .resolve_anchored(&db, AnchoredPath::new(a, "../b.py"));
+
+        assert_eq!(resolved, Some(db.file(FileSystemPath::new("/src/b.py"))));
+    }
+
+    #[test]
+    fn resolve_anchored_non_existing_file_is_none() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut().write_files([("src/a.py", "")]);
+
+        let a = db.file(FileSystemPath::new("src/a.py"));
+        let resolved = db
+            .vfs()
+            .resolve_anchored(&db, AnchoredPath::new(a, "missing.py"));
+
+        assert_eq!(resolved, None);
+    }
+
+    #[test]
+    fn resolve_anchored_non_existing_file_does_not_pollute_take_changes() {
+        let mut db = TestDb::new();
+
+        db.file_system_mut().write_files([("src/a.py", "")]);
+
+        let a = db.file(FileSystemPath::new("src/a.py"));
+        // Drain the `Created` change recorded for `a.py` itself, so only the probe below remains.
+        db.vfs().take_changes();
+
+        let resolved = db
+            .vfs()
+            .resolve_anchored(&db, AnchoredPath::new(a, "missing.py"));
+
+        assert_eq!(resolved, None);
+        // The negative probe for `missing.py` shouldn't surface as a `Deleted` change: nothing
+        // was observed to change, it simply never existed.
+        assert_eq!(db.vfs().take_changes(), Vec::new());
+    }
+
+    #[test]
+    fn resolve_anchored_vendored_sibling() {
+        let mut db = TestDb::new();
+
+        db.vfs_mut()
+            .stub_vendored([("stdlib/a.pyi", ""), ("stdlib/b.pyi", "")]);
+
+        let a = db
+            .vendored_file(VendoredPath::new("stdlib/a.pyi"))
+            .expect("Vendored file to exist.");
+
+        let resolved = db
+            .vfs()
+            .resolve_anchored(&db, AnchoredPath::new(a, "b.pyi"));
+
+        assert_eq!(
+            resolved,
+            db.vendored_file(VendoredPath::new("stdlib/b.pyi"))
+        );
+    }
+
+    #[test]
+    fn real_vendored_vfs_reads_from_zip_archive() {
+        use std::io::Write;
+
+        let mut buffer = Vec::new();
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(&mut buffer));
+        writer
+            .start_file("stdlib/builtins.pyi", zip::write::FileOptions::default())
+            .unwrap();
+        writer.write_all(b"class object: ...").unwrap();
+        writer.finish().unwrap();
+
+        // `VendoredVfs::Real` holds `'static` bytes because it's built from bytes embedded in
+        // the binary; leaking the buffer gives the test the same shape.
+        let bytes: &'static [u8] = buffer.leak();
+        let vendored = super::VendoredVfs::Real(bytes);
+
+        let builtins = VendoredPath::new("stdlib/builtins.pyi");
+        let missing = VendoredPath::new("stdlib/missing.pyi");
+
+        assert_eq!(
+            vendored.read(builtins),
+            Some("class object: ...".to_string())
+        );
+        assert!(vendored.revision(builtins).is_some());
+        assert_eq!(vendored.read(missing), None);
+        assert_eq!(vendored.revision(missing), None);
+    }
 }
@@ -0,0 +1,257 @@
+//! Loading and watching files from the file system.
+//!
+//! The [`Vfs`](super::Vfs) performs IO only on demand: it has no notion of a file until something
+//! calls [`Vfs::file`](super::Vfs::file), and no notion of a file changing unless told so
+//! explicitly. A long-running server instead wants to discover every relevant file up front and
+//! be notified as they change on disk. [`Handle`] is the object-safe trait that abstracts over how
+//! that discovery and watching happens, so that this crate doesn't need to hard-depend on a
+//! specific watcher backend (a `notify`-backed implementation is the natural choice for real runs,
+//! while tests can drive a manual implementation instead).
+
+use std::fmt;
+
+use crate::file_system::FileSystemPathBuf;
+use crate::FxDashMap;
+
+/// The directory roots to load and watch, together with the glob patterns (relative to each root)
+/// that select which files within those roots are relevant.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Config {
+    /// The directories to scan and watch.
+    pub roots: Vec<FileSystemPathBuf>,
+
+    /// Glob patterns, relative to each root, selecting which files to load (e.g. `**/*.py`).
+    pub include: Vec<String>,
+}
+
+/// A loader/watcher backend.
+///
+/// A [`Handle`] is configured with a [`Config`] describing the roots and patterns to watch. Once
+/// configured, it performs an initial scan of those roots (delivered as [`ChangeKind::Created`]
+/// changes) and then asynchronously delivers further batches of [`Change`]s as files are created,
+/// modified, or deleted on disk.
+///
+/// The trait is object-safe and swappable: the real binary wires up a `notify`-backed
+/// implementation, while tests can use a handle that delivers changes manually.
+pub trait Handle: fmt::Debug {
+    /// Creates a new, unconfigured handle that delivers change batches to `sender`.
+    fn spawn(sender: Box<dyn Fn(Vec<Change>) + Send>) -> Self
+    where
+        Self: Sized;
+
+    /// Sets the roots and patterns to load and watch, replacing any previous configuration, and
+    /// triggers the initial scan.
+    fn set_config(&mut self, config: Config);
+}
+
+/// A single file system change observed by a [`Handle`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    /// The path that changed.
+    pub path: FileSystemPathBuf,
+
+    /// What happened to `path`.
+    pub kind: ChangeKind,
+
+    /// The file's content at the time of the change, if the [`Handle`] already read it (e.g.
+    /// during the initial scan). `None` means the consumer should read the content lazily.
+    pub content: Option<String>,
+}
+
+impl Change {
+    /// Creates a change with no pre-read content.
+    pub fn new(path: FileSystemPathBuf, kind: ChangeKind) -> Self {
+        Self {
+            path,
+            kind,
+            content: None,
+        }
+    }
+
+    /// Attaches content that was already read by the [`Handle`], e.g. during the initial scan.
+    #[must_use]
+    pub fn with_content(mut self, content: String) -> Self {
+        self.content = Some(content);
+        self
+    }
+}
+
+/// What happened to a file observed by a [`Handle`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// A [`Handle`] that performs no scan of its own; tests construct one and call
+/// [`TestHandle::notify`] to push [`Change`]s as if they had just been observed on disk.
+#[derive(Debug)]
+pub struct TestHandle {
+    sender: Box<dyn Fn(Vec<Change>) + Send>,
+}
+
+impl TestHandle {
+    /// Delivers `changes` to whoever created this handle.
+    pub fn notify(&self, changes: Vec<Change>) {
+        (self.sender)(changes);
+    }
+}
+
+impl Handle for TestHandle {
+    fn spawn(sender: Box<dyn Fn(Vec<Change>) + Send>) -> Self {
+        Self { sender }
+    }
+
+    fn set_config(&mut self, _config: Config) {
+        // The test handle never scans on its own; tests call `notify` directly instead.
+    }
+}
+
+/// A [`Handle`] that performs a real, if simplistic, initial scan over a fixed in-memory set of
+/// files, then behaves like [`TestHandle`] for anything delivered afterwards via [`notify`](Self::notify).
+///
+/// [`Handle`] promises that a freshly configured handle delivers an initial scan as
+/// [`ChangeKind::Created`] changes, but [`TestHandle`] skips that step entirely. This handle
+/// exists so that promise - and the scan -> [`Vfs::apply_changes`](super::Vfs::apply_changes)
+/// path it feeds - has a test double to run against. Matching is intentionally simple (root
+/// prefix plus include suffix) rather than a full glob matcher, since it only needs to be
+/// expressive enough to drive unit tests.
+#[derive(Debug)]
+pub struct ScanningTestHandle {
+    sender: Box<dyn Fn(Vec<Change>) + Send>,
+    files: FxDashMap<FileSystemPathBuf, String>,
+}
+
+impl ScanningTestHandle {
+    /// Registers `path` as present with `contents`, so it's picked up by the initial scan if it
+    /// matches the roots/patterns the handle is later configured with.
+    pub fn insert(&self, path: FileSystemPathBuf, contents: String) {
+        self.files.insert(path, contents);
+    }
+
+    /// Delivers `changes` as if they had just been observed on disk, same as [`TestHandle::notify`].
+    pub fn notify(&self, changes: Vec<Change>) {
+        (self.sender)(changes);
+    }
+
+    fn matches(config: &Config, path: &FileSystemPathBuf) -> bool {
+        let path = path.as_str();
+
+        let under_root = config.roots.iter().any(|root| {
+            let root = root.as_str();
+            path == root || path.starts_with(&format!("{root}/"))
+        });
+
+        under_root
+            && config
+                .include
+                .iter()
+                .any(|pattern| {
+                    path.ends_with(pattern.trim_start_matches("**/").trim_start_matches('*'))
+                })
+    }
+}
+
+impl Handle for ScanningTestHandle {
+    fn spawn(sender: Box<dyn Fn(Vec<Change>) + Send>) -> Self {
+        Self {
+            sender,
+            files: FxDashMap::default(),
+        }
+    }
+
+    fn set_config(&mut self, config: Config) {
+        let changes = self
+            .files
+            .iter()
+            .filter(|entry| Self::matches(&config, entry.key()))
+            .map(|entry| {
+                Change::new(entry.key().clone(), ChangeKind::Created)
+                    .with_content(entry.value().clone())
+            })
+            .collect();
+
+        (self.sender)(changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{ChangeKind, Config, Handle, ScanningTestHandle};
+    use crate::file_system::{FileSystemPath, FileSystemPathBuf};
+    use crate::tests::TestDb;
+    use crate::vfs::FileStatus;
+    use crate::Db;
+
+    #[test]
+    fn initial_scan_reports_only_files_matching_roots_and_include() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_for_sender = received.clone();
+
+        let mut handle = ScanningTestHandle::spawn(Box::new(move |changes| {
+            received_for_sender.lock().unwrap().extend(changes);
+        }));
+
+        handle.insert(
+            FileSystemPathBuf::from("src/a.py"),
+            "print('a')".to_string(),
+        );
+        handle.insert(
+            FileSystemPathBuf::from("src/a.txt"),
+            "wrong extension".to_string(),
+        );
+        handle.insert(
+            FileSystemPathBuf::from("other/b.py"),
+            "outside root".to_string(),
+        );
+
+        handle.set_config(Config {
+            roots: vec![FileSystemPathBuf::from("src")],
+            include: vec!["**/*.py".to_string()],
+        });
+
+        let changes = received.lock().unwrap().clone();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, FileSystemPathBuf::from("src/a.py"));
+        assert_eq!(changes[0].kind, ChangeKind::Created);
+        assert_eq!(changes[0].content.as_deref(), Some("print('a')"));
+    }
+
+    #[test]
+    fn initial_scan_changes_apply_to_vfs() {
+        let mut db = TestDb::new();
+        db.file_system_mut()
+            .write_files([("src/a.py", "print('a')")]);
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let received_for_sender = received.clone();
+
+        let mut handle = ScanningTestHandle::spawn(Box::new(move |changes| {
+            received_for_sender.lock().unwrap().extend(changes);
+        }));
+
+        handle.insert(
+            FileSystemPathBuf::from("src/a.py"),
+            "print('a')".to_string(),
+        );
+
+        handle.set_config(Config {
+            roots: vec![FileSystemPathBuf::from("src")],
+            include: vec!["**/*.py".to_string()],
+        });
+
+        let scanned = std::mem::take(&mut *received.lock().unwrap());
+
+        let vfs = db.vfs();
+        vfs.apply_changes(&mut db, scanned);
+
+        let test = db.file(FileSystemPath::new("src/a.py"));
+
+        assert_eq!(test.status(&db), FileStatus::Exists);
+        assert_eq!(&test.read(&db), "print('a')");
+    }
+}